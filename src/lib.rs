@@ -1,21 +1,163 @@
-use std::{env, error::Error, fs};
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{BufRead, BufReader},
+};
+
+use regex::{Regex, RegexBuilder};
 
 /// Run the program logic, given a config object.
-/// Will perform the correct search and print the result to stdout.
+///
+/// Each file is streamed through a `BufReader` line by line rather than read
+/// into memory all at once, so memory use stays bounded even for very large
+/// files.
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.file_path)?;
+    let mut files = Vec::new();
+    for path in &config.paths {
+        collect_files(path, config.recursive, &mut files);
+    }
+
+    let prefix_paths = files.len() > 1 || config.recursive;
+
+    for file_path in &files {
+        // A bad path, permission error, etc. here just produces silent empty
+        // output for that file rather than a diagnostic like real grep's
+        // "No such file or directory" on stderr.
+        let file = match fs::File::open(file_path) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+
+        let mut match_count = 0usize;
+        stream_matches(&config, BufReader::new(file), |line_number, line| {
+            match_count += 1;
+            if !config.count {
+                println!(
+                    "{}",
+                    format_match(file_path, prefix_paths, line_number, config.line_number, line)
+                );
+            }
+        });
+
+        if config.count {
+            println!("{}", format_count(file_path, prefix_paths, match_count));
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats a single matching line the way `run` prints it, applying the
+/// `path:`/`line_number:` prefixes requested by `prefix_paths`/`show_line_number`.
+fn format_match(
+    file_path: &str,
+    prefix_paths: bool,
+    line_number: usize,
+    show_line_number: bool,
+    line: &str,
+) -> String {
+    match (prefix_paths, show_line_number) {
+        (true, true) => format!("{file_path}:{line_number}:{line}"),
+        (true, false) => format!("{file_path}:{line}"),
+        (false, true) => format!("{line_number}:{line}"),
+        (false, false) => line.to_string(),
+    }
+}
 
-    let results = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
+/// Formats the total match count the way `run` prints it in `-c`/`--count` mode.
+fn format_count(file_path: &str, prefix_paths: bool, count: usize) -> String {
+    if prefix_paths {
+        format!("{file_path}:{count}")
     } else {
-        search(&config.query, &contents)
+        format!("{count}")
+    }
+}
+
+/// Streams `reader` one line at a time, invoking `on_match` with the 1-based
+/// line number and text of every line that matches `config` (honoring regex
+/// mode, case-insensitivity, and invert-match). Lines that aren't valid UTF-8
+/// are skipped rather than aborting the whole stream.
+fn stream_matches(config: &Config, reader: impl BufRead, mut on_match: impl FnMut(usize, &str)) {
+    let lower_query = lower_query(config);
+    for (i, line) in reader.lines().enumerate() {
+        let Ok(line) = line else { continue };
+        if config_matches(config, &line, lower_query.as_deref()) != config.invert_match {
+            on_match(i + 1, &line);
+        }
+    }
+}
+
+/// Returns the 1-based line number and text of every line in `contents` that
+/// matches `config`. Operates on an in-memory string; see `stream_matches`
+/// for the streaming equivalent used by `run`.
+#[cfg(test)]
+fn matching_lines<'a>(config: &Config, contents: &'a str) -> Vec<(usize, &'a str)> {
+    let lower_query = lower_query(config);
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| config_matches(config, line, lower_query.as_deref()) != config.invert_match)
+        .collect()
+}
+
+/// Lowercases `config.query` once up front for case-insensitive substring
+/// matching, so the per-line scan in `stream_matches`/`matching_lines` never
+/// reallocates it.
+fn lower_query(config: &Config) -> Option<String> {
+    (config.ignore_case && matches!(config.mode, Mode::Substring)).then(|| config.query.to_lowercase())
+}
+
+fn config_matches(config: &Config, line: &str, lower_query: Option<&str>) -> bool {
+    match &config.mode {
+        Mode::Regex(regex) => regex.is_match(line),
+        Mode::Substring => match lower_query {
+            Some(query) => line.to_lowercase().contains(query),
+            None => line.contains(&config.query),
+        },
+    }
+}
+
+/// Expands `path` into the list of files to search, pushing onto `files`.
+///
+/// Plain files are pushed as-is. Directories are only descended into when
+/// `recursive` is set, in which case subdirectories are walked too; entries
+/// that can't be read (missing path, permissions, etc.) are skipped.
+fn collect_files(path: &str, recursive: bool, files: &mut Vec<String>) {
+    // symlink_metadata (unlike metadata) doesn't resolve symlinks, so a
+    // symlink is reported as a symlink rather than what it points at; we skip
+    // it instead of following it, matching grep's default recursive
+    // behavior and avoiding unbounded recursion on a symlink cycle.
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        // Missing path, permission denied, etc. produce silent empty output
+        // rather than a diagnostic, same as an unreadable file further down.
+        Err(_) => return,
     };
 
-    for line in results {
-        println!("{line}");
+    if metadata.is_symlink() {
+        return;
     }
 
-    Ok(())
+    if !metadata.is_dir() {
+        files.push(path.to_string());
+        return;
+    }
+
+    if !recursive {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if let Some(entry_path) = entry.path().to_str() {
+            collect_files(entry_path, recursive, files);
+        }
+    }
 }
 
 /// Performs a case-sensitive search for the query in the contents file, and 
@@ -34,9 +176,18 @@ pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
 /// assert_eq!(results, vec!["safe, fast, productive."]);
 /// ```
 pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_indexed(query, contents)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect()
+}
+
+fn search_indexed<'a>(query: &str, contents: &'a str) -> Vec<(usize, &'a str)> {
     contents
         .lines()
-        .filter(|line| line.contains(query))
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| line.contains(query))
         .collect()
 }
 
@@ -57,35 +208,100 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
 /// assert_eq!(results, vec!["safe, fast, productive."]);
 /// ```
 pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_case_insensitive_indexed(query, contents)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect()
+}
+
+fn search_case_insensitive_indexed<'a>(query: &str, contents: &'a str) -> Vec<(usize, &'a str)> {
     let query = query.to_lowercase();
-    let mut results = Vec::new();
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
-        }
-    }
-    results
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| line.to_lowercase().contains(&query))
+        .collect()
 }
 
 
-/// Holds the query string, the file path to be searched, and whether or not the search should be case sensitive.
+/// Selects how `query` is matched against each line.
+pub enum Mode {
+    /// Plain `line.contains(query)` style matching.
+    Substring,
+    /// `query` is compiled as a regular expression, matched with `is_match`.
+    Regex(Regex),
+}
+
+/// Holds the query string, the paths to be searched, and the search options.
 pub struct Config {
     pub query: String,
-    pub file_path: String,
+    pub paths: Vec<String>,
     pub ignore_case: bool,
+    pub recursive: bool,
+    pub mode: Mode,
+    pub line_number: bool,
+    pub count: bool,
+    pub invert_match: bool,
 }
 
 impl Config {
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
-        args.next(); // skip the program name
-        let query = args.next().ok_or("Didn't get a query string")?;
-        let file_path = args.next().ok_or("Didn't get a file path")?;
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let args = args.skip(1); // skip the program name
+
+        let mut positional = Vec::new();
+        let mut force_ignore_case = None;
+        let mut recursive = false;
+        let mut use_regex = false;
+        let mut line_number = false;
+        let mut count = false;
+        let mut invert_match = false;
+
+        for arg in args {
+            match arg.as_str() {
+                "-i" | "--ignore-case" => force_ignore_case = Some(true),
+                "-s" | "--case-sensitive" => force_ignore_case = Some(false),
+                "-r" | "--recursive" => recursive = true,
+                "-e" | "--regex" => use_regex = true,
+                "-n" | "--line-number" => line_number = true,
+                "-c" | "--count" => count = true,
+                "-v" | "--invert-match" => invert_match = true,
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("Unknown flag '{arg}'"))
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let query = positional
+            .next()
+            .ok_or_else(|| "Didn't get a query string".to_string())?;
+        let paths: Vec<String> = positional.collect();
+        if paths.is_empty() {
+            return Err("Didn't get a file path".to_string());
+        }
+        let ignore_case = force_ignore_case.unwrap_or_else(|| env::var("IGNORE_CASE").is_ok());
+
+        let mode = if use_regex {
+            let regex = RegexBuilder::new(&query)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| format!("Invalid regex '{query}': {e}"))?;
+            Mode::Regex(regex)
+        } else {
+            Mode::Substring
+        };
 
         Ok(Config {
             query,
-            file_path,
+            paths,
             ignore_case,
+            recursive,
+            mode,
+            line_number,
+            count,
+            invert_match,
         })
     }
 }
@@ -120,4 +336,280 @@ Trust me.";
             search_case_insensitive(query, contents)
         );
     }
+
+    #[test]
+    fn build_parses_ignore_case_flag() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-i"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        let config = Config::build(args).unwrap();
+
+        assert_eq!(config.query, "duct");
+        assert_eq!(config.paths, vec!["poem.txt"]);
+        assert!(config.ignore_case);
+    }
+
+    #[test]
+    fn build_case_sensitive_flag_overrides_env() {
+        env::set_var("IGNORE_CASE", "1");
+
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-s"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        let config = Config::build(args).unwrap();
+
+        assert!(!config.ignore_case);
+
+        env::remove_var("IGNORE_CASE");
+    }
+
+    #[test]
+    fn build_rejects_unknown_flag() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("--bogus"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        assert!(Config::build(args).is_err());
+    }
+
+    #[test]
+    fn build_collects_multiple_paths() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-r"),
+            String::from("duct"),
+            String::from("poem.txt"),
+            String::from("src"),
+        ]
+        .into_iter();
+
+        let config = Config::build(args).unwrap();
+
+        assert_eq!(config.paths, vec!["poem.txt", "src"]);
+        assert!(config.recursive);
+    }
+
+    #[test]
+    fn collect_files_walks_directories_recursively() {
+        let base = env::temp_dir().join(format!(
+            "minigrep_collect_files_test_{}",
+            std::process::id()
+        ));
+        let nested = base.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(base.join("a.txt"), "hello").unwrap();
+        fs::write(nested.join("b.txt"), "world").unwrap();
+        fs::write(base.join("bin.dat"), [0xff, 0xfe, 0x00, 0xff]).unwrap();
+
+        let mut files = Vec::new();
+        collect_files(base.to_str().unwrap(), true, &mut files);
+        files.sort();
+
+        let mut expected = vec![
+            base.join("a.txt").to_str().unwrap().to_string(),
+            base.join("bin.dat").to_str().unwrap().to_string(),
+            nested.join("b.txt").to_str().unwrap().to_string(),
+        ];
+        expected.sort();
+        assert_eq!(files, expected);
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn collect_files_skips_directories_without_recursive_flag() {
+        let base = env::temp_dir().join(format!(
+            "minigrep_collect_files_norecurse_test_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("a.txt"), "hello").unwrap();
+
+        let mut files = Vec::new();
+        collect_files(base.to_str().unwrap(), false, &mut files);
+
+        assert!(files.is_empty());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn collect_files_skips_symlinks_and_avoids_cycles() {
+        use std::os::unix::fs::symlink;
+
+        let base = env::temp_dir().join(format!(
+            "minigrep_collect_files_symlink_test_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("a.txt"), "hello").unwrap();
+        symlink(&base, base.join("self_loop")).unwrap();
+
+        let mut files = Vec::new();
+        collect_files(base.to_str().unwrap(), true, &mut files);
+
+        assert_eq!(files, vec![base.join("a.txt").to_str().unwrap().to_string()]);
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn build_compiles_regex_mode() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-e"),
+            String::from("d.ct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        let config = Config::build(args).unwrap();
+
+        match &config.mode {
+            Mode::Regex(regex) => assert!(regex.is_match("duct")),
+            Mode::Substring => panic!("expected regex mode"),
+        }
+    }
+
+    #[test]
+    fn build_rejects_malformed_regex() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-e"),
+            String::from("("),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        assert!(Config::build(args).is_err());
+    }
+
+    #[test]
+    fn build_parses_reporting_flags() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-n"),
+            String::from("-c"),
+            String::from("-v"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+
+        let config = Config::build(args).unwrap();
+
+        assert!(config.line_number);
+        assert!(config.count);
+        assert!(config.invert_match);
+    }
+
+    #[test]
+    fn format_match_covers_all_prefix_combinations() {
+        assert_eq!(
+            format_match("poem.txt", true, 2, true, "safe, fast, productive."),
+            "poem.txt:2:safe, fast, productive."
+        );
+        assert_eq!(
+            format_match("poem.txt", true, 2, false, "safe, fast, productive."),
+            "poem.txt:safe, fast, productive."
+        );
+        assert_eq!(
+            format_match("poem.txt", false, 2, true, "safe, fast, productive."),
+            "2:safe, fast, productive."
+        );
+        assert_eq!(
+            format_match("poem.txt", false, 2, false, "safe, fast, productive."),
+            "safe, fast, productive."
+        );
+    }
+
+    #[test]
+    fn format_count_covers_both_prefix_modes() {
+        assert_eq!(format_count("poem.txt", true, 3), "poem.txt:3");
+        assert_eq!(format_count("poem.txt", false, 3), "3");
+    }
+
+    #[test]
+    fn matching_lines_reports_line_numbers() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+        let config = Config::build(args).unwrap();
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.";
+
+        assert_eq!(
+            matching_lines(&config, contents),
+            vec![(2, "safe, fast, productive.")]
+        );
+    }
+
+    #[test]
+    fn matching_lines_honors_invert_match() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("-v"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+        let config = Config::build(args).unwrap();
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.";
+
+        assert_eq!(
+            matching_lines(&config, contents),
+            vec![(1, "Rust:"), (3, "Pick three.")]
+        );
+    }
+
+    #[test]
+    fn stream_matches_yields_same_results_as_matching_lines() {
+        let args = vec![
+            String::from("minigrep"),
+            String::from("duct"),
+            String::from("poem.txt"),
+        ]
+        .into_iter();
+        let config = Config::build(args).unwrap();
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.";
+
+        let mut streamed = Vec::new();
+        stream_matches(&config, contents.as_bytes(), |line_number, line| {
+            streamed.push((line_number, line.to_string()));
+        });
+
+        assert_eq!(streamed, vec![(2, "safe, fast, productive.".to_string())]);
+
+        let in_memory: Vec<(usize, String)> = matching_lines(&config, contents)
+            .into_iter()
+            .map(|(line_number, line)| (line_number, line.to_string()))
+            .collect();
+        assert_eq!(streamed, in_memory);
+    }
 }